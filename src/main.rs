@@ -1,7 +1,9 @@
 use anyhow::Context;
 use anyhow::{Error, Result};
+use chrono::{DateTime, Local, Timelike};
 use clearscreen::clear;
 use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::ffi::OsStr;
 use std::fs;
@@ -11,6 +13,9 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::process;
 use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use walkdir::WalkDir;
 const PATH_OF_CONFIG: &str = "~/.config/wezback";
 const EXTENSIONS: [&str; 12] = [
     "jpeg", "jpg", "png", "gif", "bmp", "ico", "webp", "tiff", "pnm", "dds", "tga", "farbfeld",
@@ -29,68 +34,470 @@ fn expand_tilde(path: &str) -> Result<String, Error> {
     Ok(path.to_string())
 }
 
-fn load_wezback_config() -> Result<(String, String, String)> {
-    let config = expand_tilde(PATH_OF_CONFIG)?;
-    let config_contents = fs::read_to_string(&config)?;
+/// Configuration loaded from the path returned by [`resolve_config_path`].
+#[derive(Debug, Deserialize)]
+struct Config {
+    images: String,
+    wezlua: String,
+    animations: String,
+    #[serde(default)]
+    times: Vec<String>,
+    #[serde(default)]
+    interval: Option<String>,
+    #[serde(default)]
+    recursive: bool,
+}
+
+impl Config {
+    /// Expands `~` in each path field after deserialization.
+    fn expand_paths(mut self) -> Result<Self> {
+        self.images = expand_tilde(&self.images)?;
+        self.wezlua = expand_tilde(&self.wezlua)?;
+        self.animations = expand_tilde(&self.animations)?;
+        Ok(self)
+    }
+}
 
-    let mut images = None;
-    let mut wezlua = None;
-    let mut animations = None;
+const XDG_CONFIG_DIRS_DEFAULT: &str = "/etc/xdg";
 
-    for line in config_contents.lines() {
-        if let Some(value) = line.strip_prefix("images = ") {
-            images = Some(expand_tilde(value.trim_matches('"')));
-        } else if let Some(value) = line.strip_prefix("wezlua = ") {
-            wezlua = Some(expand_tilde(value.trim_matches('"')));
-        } else if let Some(value) = line.strip_prefix("animations = ") {
-            animations = Some(expand_tilde(value.trim_matches('"')));
+/// Resolves the config file following the XDG Base Directory spec: first
+/// `$XDG_CONFIG_HOME/wezback/config.toml`, then each `$XDG_CONFIG_DIRS`
+/// entry (default `/etc/xdg`), then the legacy `~/.config/wezback` path.
+/// Returns the first of these that exists on disk.
+fn resolve_config_path() -> Result<PathBuf> {
+    if let Some(candidate) = xdg_config_home()?.map(|base| base.join("wezback/config.toml")) {
+        if candidate.is_file() {
+            return Ok(candidate);
         }
     }
 
-    Ok((
-        images.context("Missing 'images' key in config")??,
-        wezlua.context("Missing 'wezlua' key in config")??,
-        animations.context("Missing 'images' key in config")??,
-    ))
+    let config_dirs =
+        env::var("XDG_CONFIG_DIRS").unwrap_or_else(|_| XDG_CONFIG_DIRS_DEFAULT.to_string());
+    for dir in config_dirs.split(':').filter(|d| !d.is_empty()) {
+        let candidate = Path::new(dir).join("wezback/config.toml");
+        if candidate.is_file() {
+            return Ok(candidate);
+        }
+    }
+
+    let legacy = PathBuf::from(expand_tilde(PATH_OF_CONFIG)?);
+    if legacy.is_file() {
+        return Ok(legacy);
+    }
+
+    anyhow::bail!(
+        "No config found in $XDG_CONFIG_HOME, $XDG_CONFIG_DIRS or '{PATH_OF_CONFIG}'; run with --init to create one"
+    )
 }
 
-fn load_list_of_images(path_of_images: &str) -> Result<Vec<String>, Error> {
-    let expanded_path_of_images = expand_tilde(path_of_images)?;
+/// Returns `$XDG_CONFIG_HOME`, falling back to `~/.config` per the XDG spec
+/// when the variable is unset or empty.
+fn xdg_config_home() -> Result<Option<PathBuf>> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        return Ok(Some(PathBuf::from(dir)));
+    }
+
+    match env::var_os("HOME") {
+        Some(home) => Ok(Some(Path::new(&home).join(".config"))),
+        None => Ok(None),
+    }
+}
+
+const DEFAULT_CONFIG: &str = r#"# Wezback configuration.
+
+# Path to the directory of static wallpapers, absolute or relative to home.
+images = "~/Pictures/wallpapers"
+
+# Location of the wezterm.lua configuration file to rewrite on rotation.
+wezlua = "~/.config/wezterm/wezterm.lua"
+
+# Path to the directory of animated wallpapers, absolute or relative to home.
+animations = "~/Pictures/wallpapers/animated"
+
+# Times of day (HH:MM) at which --daemon rotates the wallpaper.
+# times = ["09:00", "13:30", "18:00"]
+
+# Fixed rotation interval used by --daemon when 'times' is absent.
+# interval = "30m"
+
+# Recurse into subdirectories when collecting wallpapers.
+# recursive = false
+"#;
+
+fn load_wezback_config() -> Result<Config> {
+    let config = resolve_config_path()?;
+    let config_contents = fs::read_to_string(&config)
+        .with_context(|| format!("Could not read config file at '{}'", config.display()))?;
 
+    toml::from_str::<Config>(&config_contents)
+        .context("Could not parse config file")?
+        .expand_paths()
+}
+
+/// Writes a commented default config to `$XDG_CONFIG_HOME/wezback/config.toml`
+/// (falling back to `~/.config/wezback/config.toml`) if one doesn't already
+/// exist, creating any missing parent directories.
+fn init_config() -> Result<()> {
+    let base = xdg_config_home()?.context("Unable to determine a config directory")?;
+    let wezback_dir = base.join("wezback");
+    let path = wezback_dir.join("config.toml");
+
+    if path.exists() {
+        println!("Config already exists at {}", path.display());
+        return Ok(());
+    }
+
+    // The legacy, pre-XDG config was the flat file `~/.config/wezback`, which
+    // occupies the same path as our directory. Migrate it instead of letting
+    // `create_dir_all` fail on it.
+    if wezback_dir.is_file() {
+        let legacy_contents = fs::read(&wezback_dir)?;
+        fs::remove_file(&wezback_dir)?;
+        fs::create_dir_all(&wezback_dir)?;
+        fs::write(&path, legacy_contents)?;
+        println!(
+            "Migrated legacy config from {} to {}",
+            wezback_dir.display(),
+            path.display()
+        );
+        return Ok(());
+    }
+
+    fs::create_dir_all(&wezback_dir)?;
+    fs::write(&path, DEFAULT_CONFIG)?;
+    println!("Wrote default config to {}", path.display());
+    Ok(())
+}
+
+/// Parses a duration string such as `"30m"`, `"45s"` or `"2h"` into a [`Duration`].
+fn parse_interval(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    anyhow::ensure!(!value.is_empty(), "Interval must not be empty");
+    let (number, suffix) = value.split_at(value.len() - 1);
+    let amount: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid interval '{value}'"))?;
+    let seconds = match suffix {
+        "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 3600,
+        _ => anyhow::bail!("Interval '{value}' must end in 's', 'm' or 'h'"),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parses a `HH:MM` time-of-day string into seconds since midnight.
+fn parse_time_of_day(value: &str) -> Result<u32> {
+    let (hours, minutes) = value
+        .split_once(':')
+        .with_context(|| format!("Invalid time '{value}', expected HH:MM"))?;
+    let hours: u32 = hours
+        .parse()
+        .with_context(|| format!("Invalid hour in time '{value}'"))?;
+    let minutes: u32 = minutes
+        .parse()
+        .with_context(|| format!("Invalid minute in time '{value}'"))?;
+    anyhow::ensure!(hours < 24 && minutes < 60, "Time '{value}' out of range");
+    Ok(hours * 3600 + minutes * 60)
+}
+
+/// Computes the duration to sleep until the next of the given `HH:MM` times
+/// (evaluated in the local timezone), wrapping around to tomorrow for any
+/// times that have already passed today.
+fn duration_until_next(times: &[String], now: DateTime<Local>) -> Result<Duration> {
+    duration_until_next_from_seconds(times, now.time().num_seconds_from_midnight())
+}
+
+/// Pure helper behind [`duration_until_next`], taking seconds-since-local-midnight
+/// directly so the wrap-around math can be unit tested without touching the clock.
+fn duration_until_next_from_seconds(times: &[String], since_midnight: u32) -> Result<Duration> {
+    times
+        .iter()
+        .map(|t| parse_time_of_day(t))
+        .collect::<Result<Vec<u32>>>()?
+        .into_iter()
+        .map(|slot| {
+            if slot > since_midnight {
+                (slot - since_midnight) as u64
+            } else {
+                (slot + 86400 - since_midnight) as u64
+            }
+        })
+        .min()
+        .map(Duration::from_secs)
+        .context("No scheduled times configured")
+}
+
+/// Runs the rotator forever, rotating the wallpaper at each scheduled `times`
+/// slot, or every `interval` if no `times` are configured.
+fn run_daemon(
+    images: &[String],
+    path_of_wezlua: &str,
+    times: &[String],
+    interval: Option<&str>,
+) -> Result<()> {
+    loop {
+        let sleep_duration = if !times.is_empty() {
+            duration_until_next(times, Local::now())?
+        } else if let Some(interval) = interval {
+            parse_interval(interval)?
+        } else {
+            anyhow::bail!("Daemon mode requires a 'times' or 'interval' key in the config");
+        };
+
+        thread::sleep(sleep_duration);
+
+        if let Some(new_image) = select_random_wallpaper(images)? {
+            update_config_file(path_of_wezlua, &new_image)?;
+        } else {
+            eprintln!("Could not select a wallpaper.");
+        }
+    }
+}
+
+/// An image index cached on disk, tagged with the wallpaper directory
+/// identity, its mtime, and scan mode so it can be invalidated when any
+/// of those change.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedIndex {
+    dir: PathBuf,
+    dir_mtime: u64,
+    recursive: bool,
+    images: Vec<String>,
+}
+
+/// Returns `$XDG_CACHE_HOME`, falling back to `~/.cache` per the XDG spec
+/// when the variable is unset or empty.
+fn xdg_cache_home() -> Result<Option<PathBuf>> {
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|v| !v.is_empty()) {
+        return Ok(Some(PathBuf::from(dir)));
+    }
+
+    match env::var_os("HOME") {
+        Some(home) => Ok(Some(Path::new(&home).join(".cache"))),
+        None => Ok(None),
+    }
+}
+
+/// Returns a per-directory cache file, keyed off a hash of the canonicalized
+/// wallpaper directory so `images` and `animations` don't share a slot.
+fn index_cache_path(wallpaper_dir: &Path) -> Result<PathBuf> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let base = xdg_cache_home()?.context("Unable to determine a cache directory")?;
+    let mut hasher = DefaultHasher::new();
+    wallpaper_dir.hash(&mut hasher);
+    Ok(base.join(format!("wezback/index-{:x}.bin", hasher.finish())))
+}
+
+/// Returns the mtime of `path` to use for cache invalidation, in seconds
+/// since the epoch. When `recursive` is set, nested subdirectories are
+/// walked too and the *latest* mtime in the tree is used, since adding or
+/// removing a file in a subdirectory doesn't touch the root's own mtime.
+fn dir_mtime_secs(path: &Path, recursive: bool) -> Result<u64> {
+    let mut latest = fs::metadata(path)?.modified()?;
+
+    if recursive {
+        for entry in WalkDir::new(path).into_iter().filter_map(|entry| entry.ok()) {
+            if let Ok(metadata) = entry.metadata() {
+                if let Ok(modified) = metadata.modified() {
+                    latest = latest.max(modified);
+                }
+            }
+        }
+    }
+
+    Ok(latest.duration_since(std::time::UNIX_EPOCH)?.as_secs())
+}
+
+fn load_list_of_images(
+    path_of_images: &str,
+    refresh: bool,
+    recursive: bool,
+) -> Result<Vec<String>, Error> {
+    let expanded_path_of_images = expand_tilde(path_of_images)?;
     let wallpaper_dir = PathBuf::from_str(&expanded_path_of_images)?.canonicalize()?;
+    let dir_mtime = dir_mtime_secs(&wallpaper_dir, recursive)?;
+    let cache_path = index_cache_path(&wallpaper_dir)?;
 
-    let paths = fs::read_dir(&wallpaper_dir)?;
+    if !refresh {
+        if let Some(cached) = read_cached_index(&cache_path, &wallpaper_dir, dir_mtime, recursive)?
+        {
+            return Ok(cached);
+        }
+    }
 
-    let mut images = Vec::new();
+    let images = scan_images(&wallpaper_dir, recursive)?;
+    write_cached_index(&cache_path, &wallpaper_dir, dir_mtime, recursive, &images)?;
+    Ok(images)
+}
+
+/// Reads the index cache, returning `None` if it's missing, corrupt, or
+/// stale (different directory, mtime, or scan mode than requested).
+fn read_cached_index(
+    cache_path: &Path,
+    wallpaper_dir: &Path,
+    dir_mtime: u64,
+    recursive: bool,
+) -> Result<Option<Vec<String>>> {
+    let bytes = match fs::read(cache_path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(None),
+    };
 
+    let cached: CachedIndex = match bincode::deserialize(&bytes) {
+        Ok(cached) => cached,
+        Err(_) => return Ok(None),
+    };
+
+    if cached.dir == wallpaper_dir && cached.dir_mtime == dir_mtime && cached.recursive == recursive
+    {
+        Ok(Some(cached.images))
+    } else {
+        Ok(None)
+    }
+}
+
+fn write_cached_index(
+    cache_path: &Path,
+    wallpaper_dir: &Path,
+    dir_mtime: u64,
+    recursive: bool,
+    images: &[String],
+) -> Result<()> {
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let cached = CachedIndex {
+        dir: wallpaper_dir.to_path_buf(),
+        dir_mtime,
+        recursive,
+        images: images.to_vec(),
+    };
+    fs::write(cache_path, bincode::serialize(&cached)?)?;
+    Ok(())
+}
+
+/// Collects wallpaper paths matching [`EXTENSIONS`], stripped of the home
+/// prefix. Recurses into subdirectories when `recursive` is set.
+fn scan_images(wallpaper_dir: &Path, recursive: bool) -> Result<Vec<String>, Error> {
     let home = env::var_os("HOME")
         .ok_or_else(|| anyhow::anyhow!("HOME environment variable not found"))?;
     let home_path = Path::new(&home);
 
-    for entry in paths {
-        let entry = entry?;
-        let path = entry.path();
-
-        let extension = match path.extension().and_then(OsStr::to_str) {
-            Some(ext) => ext,
-            None => continue,
-        };
-
-        if EXTENSIONS.contains(&extension) {
-            let stripped_path = match path.strip_prefix(home_path) {
-                Ok(stripped) => stripped.to_string_lossy().to_string(),
-                Err(_) => continue,
-            };
+    let mut images = Vec::new();
 
-            images.push(stripped_path);
+    if recursive {
+        for entry in WalkDir::new(wallpaper_dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+        {
+            push_if_image(entry.path(), home_path, &mut images);
+        }
+    } else {
+        for entry in fs::read_dir(wallpaper_dir)? {
+            push_if_image(&entry?.path(), home_path, &mut images);
         }
     }
 
     Ok(images)
 }
 
-fn select_random_wallpaper(images: &[String]) -> Option<String> {
-    images.choose(&mut rand::rng()).map(|s| s.to_string())
+fn push_if_image(path: &Path, home_path: &Path, images: &mut Vec<String>) {
+    let Some(extension) = path.extension().and_then(OsStr::to_str) else {
+        return;
+    };
+
+    if !EXTENSIONS.contains(&extension) {
+        return;
+    }
+
+    if let Ok(stripped) = path.strip_prefix(home_path) {
+        images.push(stripped.to_string_lossy().to_string());
+    }
+}
+
+/// Caps how many recently-shown paths are remembered, so the history doesn't
+/// grow unbounded for very large wallpaper collections.
+const HISTORY_WINDOW_CAP: usize = 64;
+
+fn history_path() -> Result<PathBuf> {
+    let base = xdg_cache_home()?.context("Unable to determine a cache directory")?;
+    Ok(base.join("wezback/history"))
+}
+
+fn read_history(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .map(|contents| contents.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn write_history(path: &Path, history: &[String]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, history.join("\n"))?;
+    Ok(())
+}
+
+/// Picks a wallpaper, preferring images outside the recent-history window so
+/// every wallpaper is shown once before any repeats. The history is a shuffle
+/// bag: once every image has been drawn, it empties out except for the image
+/// just shown, and the next candidates are drawn from the rest of the set.
+/// For sets larger than `HISTORY_WINDOW_CAP + 1` images the history instead
+/// keeps only the last [`HISTORY_WINDOW_CAP`] picks, so only those are
+/// avoided. `history` is pruned of stale entries and updated in place with
+/// the chosen image.
+///
+/// Pure (apart from the RNG) so the shuffle-bag behavior can be unit tested
+/// without touching the history file on disk.
+fn pick_from_history(images: &[String], history: &mut Vec<String>) -> Option<String> {
+    if images.is_empty() {
+        return None;
+    }
+
+    history.retain(|path| images.contains(path));
+
+    let window = images.len().saturating_sub(1).min(HISTORY_WINDOW_CAP);
+    if history.len() > window {
+        let excess = history.len() - window;
+        history.drain(0..excess);
+    }
+
+    let candidates: Vec<&String> = images.iter().filter(|img| !history.contains(img)).collect();
+    let chosen = match candidates.choose(&mut rand::rng()) {
+        Some(choice) => (*choice).clone(),
+        None => images
+            .choose(&mut rand::rng())
+            .expect("images is non-empty")
+            .clone(),
+    };
+
+    history.push(chosen.clone());
+    if history.len() >= images.len() {
+        // Bag exhausted: start a new one, remembering only the image just shown.
+        history.drain(..history.len() - 1);
+    } else if history.len() > window {
+        history.remove(0);
+    }
+
+    Some(chosen)
+}
+
+fn select_random_wallpaper(images: &[String]) -> Result<Option<String>> {
+    if images.is_empty() {
+        return Ok(None);
+    }
+
+    let history_path = history_path()?;
+    let mut history = read_history(&history_path);
+    let chosen = pick_from_history(images, &mut history);
+    write_history(&history_path, &history)?;
+
+    Ok(chosen)
 }
 
 fn update_config_file(config_path: &str, new_image: &str) -> Result<()> {
@@ -133,21 +540,44 @@ struct Args {
     /// Change wallpaper once and exit
     #[arg(short = 'o', long = "once")]
     once: bool,
+
+    /// Run in the background, rotating on the configured schedule
+    #[arg(short = 'd', long = "daemon", conflicts_with = "once")]
+    daemon: bool,
+
     /// Config help
     #[arg(short = 'c', long = "config-help")]
     config_help: bool,
+
+    /// Write a default config file to $XDG_CONFIG_HOME/wezback/config.toml
+    /// (default ~/.config/wezback/config.toml) and exit
+    #[arg(long = "init")]
+    init: bool,
+
+    /// Force a rescan of the wallpaper directories, bypassing the cached index
+    #[arg(long = "refresh")]
+    refresh: bool,
+
+    /// Recurse into subdirectories when collecting wallpapers
+    #[arg(short = 'r', long = "recursive")]
+    recursive: bool,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let (path_of_images, path_of_wezlua, animations) = load_wezback_config()?;
+    if args.init {
+        return init_config();
+    }
+
+    let config = load_wezback_config()?;
+    let recursive = args.recursive || config.recursive;
 
-    let mut images = load_list_of_images(&path_of_images)?;
+    let mut images = load_list_of_images(&config.images, args.refresh, recursive)?;
 
     if args.once {
-        if let Some(new_image) = select_random_wallpaper(&images) {
-            update_config_file(&path_of_wezlua, &new_image)?;
+        if let Some(new_image) = select_random_wallpaper(&images)? {
+            update_config_file(&config.wezlua, &new_image)?;
         } else {
             eprintln!("Could not select a wallpaper.");
         }
@@ -155,25 +585,34 @@ fn main() -> Result<()> {
     }
 
     if args.config_help {
-        let help = "Configured in ~/.config/wezback
+        let help = "Configured via $XDG_CONFIG_HOME/wezback/config.toml, $XDG_CONFIG_DIRS, or ~/.config/wezback
 images = \"[path of images, absolute or relative to home]\"
 wezlua = \"[location of the wezterm.lua configuration file, absolute or relative to home]\"
-animations = \"[location of animated images, absolute or relative to home]\""
+animations = \"[location of animated images, absolute or relative to home]\"
+times = [\"09:00\", \"13:30\", \"18:00\"] (optional, used by --daemon)
+interval = \"30m\" (optional, used by --daemon if 'times' is absent)
+recursive = false (optional, scan wallpaper directories recursively)
+
+Run with --init to write a default config."
             .to_string();
         println!("{help}");
         return Ok(());
     }
 
     if args.all {
-        let animations = load_list_of_images(&animations)?;
+        let animations = load_list_of_images(&config.animations, args.refresh, recursive)?;
         images.extend(animations);
     } else if args.no_static {
-        images = load_list_of_images(&animations)?;
+        images = load_list_of_images(&config.animations, args.refresh, recursive)?;
+    }
+
+    if args.daemon {
+        return run_daemon(&images, &config.wezlua, &config.times, config.interval.as_deref());
     }
 
     loop {
-        if let Some(new_image) = select_random_wallpaper(&images) {
-            update_config_file(&path_of_wezlua, &new_image)?;
+        if let Some(new_image) = select_random_wallpaper(&images)? {
+            update_config_file(&config.wezlua, &new_image)?;
         } else {
             eprintln!("Could not select a wallpaper.");
         }
@@ -185,3 +624,116 @@ animations = \"[location of animated images, absolute or relative to home]\""
         clear()?;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_interval_accepts_seconds_minutes_and_hours() {
+        assert_eq!(parse_interval("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(parse_interval("2h").unwrap(), Duration::from_secs(2 * 3600));
+    }
+
+    #[test]
+    fn parse_interval_rejects_empty_value() {
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("   ").is_err());
+    }
+
+    #[test]
+    fn parse_interval_rejects_unknown_suffix() {
+        assert!(parse_interval("30x").is_err());
+    }
+
+    #[test]
+    fn parse_time_of_day_reads_hours_and_minutes() {
+        assert_eq!(parse_time_of_day("09:00").unwrap(), 9 * 3600);
+        assert_eq!(parse_time_of_day("23:59").unwrap(), 23 * 3600 + 59 * 60);
+    }
+
+    #[test]
+    fn parse_time_of_day_rejects_out_of_range() {
+        assert!(parse_time_of_day("24:00").is_err());
+        assert!(parse_time_of_day("12:60").is_err());
+        assert!(parse_time_of_day("noon").is_err());
+    }
+
+    #[test]
+    fn duration_until_next_picks_the_closest_upcoming_slot() {
+        let times = vec!["09:00".to_string(), "13:30".to_string(), "18:00".to_string()];
+        let since_midnight = 10 * 3600; // 10:00
+        let duration = duration_until_next_from_seconds(&times, since_midnight).unwrap();
+        assert_eq!(duration, Duration::from_secs(3 * 3600 + 30 * 60));
+    }
+
+    #[test]
+    fn duration_until_next_wraps_around_to_tomorrow() {
+        let times = vec!["09:00".to_string()];
+        let since_midnight = 23 * 3600; // 23:00, today's slot already passed
+        let duration = duration_until_next_from_seconds(&times, since_midnight).unwrap();
+        assert_eq!(duration, Duration::from_secs(10 * 3600));
+    }
+
+    #[test]
+    fn duration_until_next_requires_at_least_one_time() {
+        assert!(duration_until_next_from_seconds(&[], 0).is_err());
+    }
+
+    #[test]
+    fn pick_from_history_avoids_recently_shown_images() {
+        let images = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut history = vec!["a".to_string(), "b".to_string()];
+
+        let chosen = pick_from_history(&images, &mut history).unwrap();
+
+        assert_eq!(chosen, "c");
+        assert!(history.contains(&"c".to_string()));
+    }
+
+    #[test]
+    fn pick_from_history_refills_once_the_bag_is_exhausted() {
+        let images = vec!["a".to_string(), "b".to_string()];
+        // The whole bag but the last shown image has already been drawn.
+        let mut history = vec!["a".to_string()];
+
+        let chosen = pick_from_history(&images, &mut history).unwrap();
+
+        // With a 2-image set the window is 1, so only "b" can be drawn next.
+        assert_eq!(chosen, "b");
+        // The bag is now exhausted and refilled, remembering only "b".
+        assert_eq!(history, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn pick_from_history_drops_stale_entries_not_in_the_image_set() {
+        let images = vec!["a".to_string(), "b".to_string()];
+        let mut history = vec!["deleted.png".to_string(), "a".to_string()];
+
+        let chosen = pick_from_history(&images, &mut history).unwrap();
+
+        assert_eq!(chosen, "b");
+        assert!(!history.contains(&"deleted.png".to_string()));
+    }
+
+    #[test]
+    fn pick_from_history_caps_the_window_for_large_collections() {
+        let images: Vec<String> = (0..(HISTORY_WINDOW_CAP + 10))
+            .map(|i| format!("img{i}"))
+            .collect();
+        let mut history = Vec::new();
+
+        for _ in 0..(HISTORY_WINDOW_CAP + 5) {
+            pick_from_history(&images, &mut history);
+        }
+
+        assert!(history.len() <= HISTORY_WINDOW_CAP);
+    }
+
+    #[test]
+    fn pick_from_history_returns_none_for_empty_image_set() {
+        let mut history = Vec::new();
+        assert_eq!(pick_from_history(&[], &mut history), None);
+    }
+}